@@ -1,4 +1,3 @@
-use std::fs::File;
 use std::io::Error as IoError;
 use std::io::Read;
 use std::io::Seek;
@@ -10,6 +9,8 @@ use pyo3::prelude::*;
 use pyo3::types::PyBytes;
 use pyo3::PyObject;
 
+use nafcodec::source::Source;
+
 // ---------------------------------------------------------------------------
 
 #[macro_export]
@@ -117,25 +118,7 @@ impl Seek for PyFileRead {
 
 // ---------------------------------------------------------------------------
 
-pub enum PyFileWrapper {
-    PyFile(PyFileRead),
-    File(File),
-}
-
-impl Read for PyFileWrapper {
-    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
-        match self {
-            PyFileWrapper::PyFile(r) => r.read(buf),
-            PyFileWrapper::File(f) => f.read(buf),
-        }
-    }
-}
-
-impl Seek for PyFileWrapper {
-    fn seek(&mut self, seek: SeekFrom) -> Result<u64, IoError> {
-        match self {
-            PyFileWrapper::PyFile(r) => r.seek(seek),
-            PyFileWrapper::File(f) => f.seek(seek),
-        }
-    }
-}
+/// `PyFileRead` is never fully memory-resident, so it keeps the default
+/// owned-bytes behavior: `Source::as_slice` always returns `None`, and
+/// every `Record::sequence`/`quality` read through it is an owned `Cow`.
+impl Source for PyFileRead {}
@@ -3,10 +3,15 @@
 // --- MaskUnit ----------------------------------------------------------------
 
 use std::borrow::Cow;
+use std::io::Read;
+use std::io::Write;
+use std::io::{self};
 use std::ops::BitOr;
 use std::ops::BitOrAssign;
 use std::fmt;
 
+use crate::extension::Annotation;
+
 /// A single masked unit with associated status decoded from the mask block.
 #[derive(Debug, Clone, PartialEq)]
 pub enum MaskUnit {
@@ -26,6 +31,14 @@ pub enum MaskUnit {
 /// secondary structure in dot-bracket notation, or protein secondary
 /// structure.
 ///
+/// ## Extensions
+///
+/// When the archive sets the [`Flag::Extended`] flag, a record may also
+/// carry typed [`Annotation`]s decoded from the extension block (see the
+/// [`extension`](crate::extension) module), such as the secondary
+/// structure mentioned above stored as structured data rather than in
+/// the quality slot.
+///
 #[derive(Debug, Clone, Default)]
 pub struct Record<'a> {
     /// The record identifier (accession number).
@@ -38,6 +51,8 @@ pub struct Record<'a> {
     pub quality: Option<Cow<'a, str>>,
     /// The record sequence length.
     pub length: Option<u64>,
+    /// Typed extension annotations, decoded when `Flag::Extended` is set.
+    pub annotations: Vec<Annotation>,
 }
 
 
@@ -53,6 +68,20 @@ pub enum FormatVersion {
     V2 = 2,
 }
 
+impl TryFrom<u8> for FormatVersion {
+    type Error = io::Error;
+    fn try_from(byte: u8) -> io::Result<Self> {
+        match byte {
+            1 => Ok(Self::V1),
+            2 => Ok(Self::V2),
+            other => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid format version: {other}"),
+            )),
+        }
+    }
+}
+
 // --- SequenceType ------------------------------------------------------------
 
 /// The type of sequence stored in a Nucleotide Archive Format file.
@@ -81,11 +110,27 @@ impl SequenceType {
     }
 }
 
+impl TryFrom<u8> for SequenceType {
+    type Error = io::Error;
+    fn try_from(byte: u8) -> io::Result<Self> {
+        match byte {
+            0 => Ok(Self::Dna),
+            1 => Ok(Self::Rna),
+            2 => Ok(Self::Protein),
+            3 => Ok(Self::Text),
+            other => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid sequence type: {other}"),
+            )),
+        }
+    }
+}
+
 // --- Flag --------------------------------------------------------------------
 
 /// A single flag inside header flags.
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Flag {
     /// A flag indicating sequence qualities are stored in the archive.
     Quality = 0x1,
@@ -169,6 +214,12 @@ impl Flags {
     pub const fn as_byte(&self) -> u8 {
         self.0
     }
+
+    /// Rebuild flags from the byte produced by [`Flags::as_byte`].
+    #[must_use]
+    pub const fn from_byte(byte: u8) -> Self {
+        Self(byte)
+    }
 }
 
 impl Default for Flags {
@@ -219,6 +270,9 @@ pub struct Header {
 }
 
 impl Header {
+    /// The magic bytes identifying a NAF archive.
+    pub const MAGIC: [u8; 4] = *b"NAF\0";
+
     /// Get the flags of the archive header.
     #[must_use]
     pub const fn flags(&self) -> Flags {
@@ -254,6 +308,64 @@ impl Header {
     pub const fn format_version(&self) -> FormatVersion {
         self.format_version
     }
+
+    /// Write this header to `writer`.
+    ///
+    /// The layout is the magic bytes, then `format_version`,
+    /// `sequence_type`, `flags`, and `name_separator` as single bytes,
+    /// then `line_length` and `number_of_sequences` as 8-byte
+    /// little-endian integers, mirroring the `u8`/`u64::to_le_bytes`
+    /// layout used by [`extension::encode_annotations`](crate::extension::encode_annotations).
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&Self::MAGIC)?;
+        writer.write_all(&[self.format_version as u8])?;
+        writer.write_all(&[self.sequence_type as u8])?;
+        writer.write_all(&[self.flags.as_byte()])?;
+        writer.write_all(&[self.name_separator as u8])?;
+        writer.write_all(&self.line_length.to_le_bytes())?;
+        writer.write_all(&self.number_of_sequences.to_le_bytes())?;
+        Ok(())
+    }
+
+    /// Read a header from `reader`, written by [`Header::write`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if the
+    /// magic bytes do not match, or if `format_version`/`sequence_type`
+    /// hold a byte this crate version does not recognize.
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != Self::MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a NAF archive"));
+        }
+
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte)?;
+        let format_version = FormatVersion::try_from(byte[0])?;
+        reader.read_exact(&mut byte)?;
+        let sequence_type = SequenceType::try_from(byte[0])?;
+        reader.read_exact(&mut byte)?;
+        let flags = Flags::from_byte(byte[0]);
+        reader.read_exact(&mut byte)?;
+        let name_separator = byte[0] as char;
+
+        let mut buf8 = [0u8; 8];
+        reader.read_exact(&mut buf8)?;
+        let line_length = u64::from_le_bytes(buf8);
+        reader.read_exact(&mut buf8)?;
+        let number_of_sequences = u64::from_le_bytes(buf8);
+
+        Ok(Self {
+            format_version,
+            sequence_type,
+            flags,
+            name_separator,
+            line_length,
+            number_of_sequences,
+        })
+    }
 }
 
 impl Default for Header {
@@ -0,0 +1,278 @@
+//! Options controlling how a NAF archive is encoded.
+
+use std::io::Write;
+use std::io::{self};
+
+use crate::data::FormatVersion;
+use crate::data::Record;
+use crate::index::FrameTable;
+
+// --- EncoderOptions ------------------------------------------------------------------
+
+/// Options controlling how an archive is written to disk.
+///
+/// Setting [`EncoderOptions::deterministic`] guarantees byte-identical
+/// output across runs and machines: all zstd framing parameters are
+/// pinned, any implementation-version or timestamp text that would
+/// otherwise leak into the `Title` or extended fields is suppressed, and
+/// records are written in a canonical order. This is meant for
+/// content-addressable storage and deduplication, where two encodes of
+/// the same sequence set must hash identically.
+#[derive(Debug, Clone)]
+pub struct EncoderOptions {
+    format_version: FormatVersion,
+    compression_level: i32,
+    line_length: u64,
+    name_separator: char,
+    deterministic: bool,
+}
+
+impl EncoderOptions {
+    /// Create new options with the crate defaults.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            format_version: FormatVersion::V1,
+            compression_level: 19,
+            line_length: 60,
+            name_separator: ' ',
+            deterministic: false,
+        }
+    }
+
+    /// Get the archive format version to encode with.
+    #[must_use]
+    pub const fn format_version(&self) -> FormatVersion {
+        self.format_version
+    }
+
+    /// Set the archive format version to encode with.
+    pub fn set_format_version(&mut self, format_version: FormatVersion) {
+        self.format_version = format_version;
+    }
+
+    /// Get the zstd compression level used for each block.
+    #[must_use]
+    pub const fn compression_level(&self) -> i32 {
+        self.compression_level
+    }
+
+    /// Set the zstd compression level used for each block.
+    pub fn set_compression_level(&mut self, compression_level: i32) {
+        self.compression_level = compression_level;
+    }
+
+    /// Get the default line length stored in the header.
+    #[must_use]
+    pub const fn line_length(&self) -> u64 {
+        self.line_length
+    }
+
+    /// Set the default line length stored in the header.
+    pub fn set_line_length(&mut self, line_length: u64) {
+        self.line_length = line_length;
+    }
+
+    /// Get the name separator stored in the header.
+    #[must_use]
+    pub const fn name_separator(&self) -> char {
+        self.name_separator
+    }
+
+    /// Set the name separator stored in the header.
+    pub fn set_name_separator(&mut self, name_separator: char) {
+        self.name_separator = name_separator;
+    }
+
+    /// Check whether deterministic encoding is enabled.
+    #[must_use]
+    pub const fn deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Enable or disable deterministic encoding.
+    ///
+    /// When enabled, [`EncoderOptions::build_encoder`] pins a fixed set of
+    /// zstd framing parameters, [`EncoderOptions::software_tag`] stops
+    /// returning crate-version text, and [`EncoderOptions::canonicalize`]
+    /// sorts records into a stable order, so the same input always
+    /// produces the same compressed bytes.
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
+
+    /// Wrap `writer` in a zstd encoder configured from these options.
+    ///
+    /// Under [`EncoderOptions::deterministic`] mode, multithreaded
+    /// compression is disabled and the content checksum is turned off:
+    /// multithreaded zstd can split a stream into a different number of
+    /// frames depending on how many worker threads are available on the
+    /// host, which would otherwise make the compressed bytes depend on
+    /// the machine the archive was written on.
+    pub fn build_encoder<'w, W: Write>(
+        &self,
+        writer: W,
+    ) -> io::Result<zstd::stream::write::Encoder<'w, W>> {
+        let mut encoder = zstd::stream::write::Encoder::new(writer, self.compression_level)?;
+        if self.deterministic {
+            encoder.multithread(0)?;
+            encoder.include_checksum(false)?;
+        }
+        Ok(encoder)
+    }
+
+    /// The text this crate would stamp into the archive's `Title` block
+    /// to identify the writer, if any.
+    ///
+    /// Returns `None` under [`EncoderOptions::deterministic`] mode, since
+    /// embedding the crate version would make two encodes of the same
+    /// input hash differently depending on which version produced them.
+    #[must_use]
+    pub fn software_tag(&self) -> Option<&'static str> {
+        if self.deterministic {
+            None
+        } else {
+            Some(concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION")))
+        }
+    }
+
+    /// Reorder `records` into a canonical order, in place.
+    ///
+    /// Under [`EncoderOptions::deterministic`] mode, records are sorted by
+    /// `id` so the same set of sequences always encodes to the same
+    /// archive, regardless of the order they were supplied in; otherwise
+    /// the records are left untouched.
+    pub fn canonicalize(&self, records: &mut [Record]) {
+        if self.deterministic {
+            records.sort_by(|a, b| a.id.cmp(&b.id));
+        }
+    }
+
+    /// Write `data` to `writer` as a series of independent zstd frames of
+    /// up to `frame_size` decompressed bytes each.
+    ///
+    /// A NAF archive normally stores a block (the sequence or quality
+    /// stream) as a single zstd stream, which has to be decompressed from
+    /// the start to reach any record in it. Splitting it into several
+    /// independent frames instead lets [`get_record_bytes`](crate::index::get_record_bytes)
+    /// decompress only the frame(s) covering a single record. The
+    /// returned [`FrameTable`] records where each frame starts, with
+    /// compressed offsets counted from the first byte this call writes.
+    /// [`FrameTable::scan`] rebuilds the table on the reading side from
+    /// the reader's stream positions, so the two tables are equal when
+    /// the block starts at offset 0 of the stream.
+    pub fn write_framed_block<W: Write>(
+        &self,
+        writer: &mut W,
+        data: &[u8],
+        frame_size: u64,
+    ) -> io::Result<FrameTable> {
+        let frame_size = usize::try_from(frame_size.max(1)).unwrap_or(usize::MAX);
+        let mut table = FrameTable::new();
+        let mut decompressed_offset = 0u64;
+        let mut compressed_offset = 0u64;
+
+        for chunk in data.chunks(frame_size) {
+            table.push(decompressed_offset, compressed_offset);
+
+            let mut counted = CountingWriter::new(&mut *writer);
+            let mut encoder = self.build_encoder(&mut counted)?;
+            encoder.write_all(chunk)?;
+            encoder.finish()?;
+
+            compressed_offset += counted.count();
+            decompressed_offset += chunk.len() as u64;
+        }
+
+        Ok(table)
+    }
+}
+
+impl Default for EncoderOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A writer that counts the bytes passed through it, used to learn each
+/// frame's compressed length as [`EncoderOptions::write_framed_block`]
+/// writes it.
+struct CountingWriter<W> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> CountingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// --- tests -------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    fn record(id: &str) -> Record<'static> {
+        Record {
+            id: Some(Cow::Owned(id.to_string())),
+            ..Record::default()
+        }
+    }
+
+    #[test]
+    fn deterministic_suppresses_the_software_tag() {
+        let mut options = EncoderOptions::new();
+        assert!(options.software_tag().is_some());
+
+        options.set_deterministic(true);
+        assert_eq!(options.software_tag(), None);
+    }
+
+    #[test]
+    fn deterministic_canonicalizes_record_order() {
+        let mut options = EncoderOptions::new();
+        let mut records = vec![record("b"), record("a"), record("c")];
+
+        options.canonicalize(&mut records);
+        assert_eq!(records[0].id, Some(Cow::Borrowed("b")));
+
+        options.set_deterministic(true);
+        options.canonicalize(&mut records);
+        let ids: Vec<_> = records.iter().map(|r| r.id.clone().unwrap()).collect();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn deterministic_encoder_omits_the_content_checksum() {
+        let mut options = EncoderOptions::new();
+        options.set_deterministic(true);
+
+        let mut buffer = Vec::new();
+        let mut encoder = options.build_encoder(&mut buffer).unwrap();
+        encoder.write_all(b"ACGT").unwrap();
+        encoder.finish().unwrap();
+
+        // Byte 4 is the frame header descriptor; bit 2 is the
+        // Content_Checksum_flag.
+        assert_eq!(buffer[4] & 0x04, 0);
+    }
+}
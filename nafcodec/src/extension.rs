@@ -0,0 +1,159 @@
+//! Forward-compatible per-record extension blocks.
+//!
+//! When [`Flag::Extended`](crate::data::Flag::Extended) is set, an archive
+//! carries an additional block of typed per-record annotations, each
+//! encoded as a `(u8 type_id, u64 payload_len, bytes payload)` triple. A
+//! decoder that does not recognize a `type_id` can simply skip
+//! `payload_len` bytes and continue reading the next annotation, so
+//! archives written by a newer crate version stay readable by an older
+//! one.
+
+use std::io::Read;
+use std::io::Write;
+use std::io::{self};
+
+// --- ExtensionType -------------------------------------------------------------------
+
+/// A registry of known extension annotation types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExtensionType {
+    /// RNA or protein secondary structure, in dot-bracket notation.
+    SecondaryStructure,
+    /// Per-base confidence scores, distinct from the `Quality` block.
+    Confidence,
+    /// An extension type this crate version does not recognize.
+    Unknown(u8),
+}
+
+impl ExtensionType {
+    const SECONDARY_STRUCTURE: u8 = 0x01;
+    const CONFIDENCE: u8 = 0x02;
+
+    /// Get the `type_id` byte identifying this extension type.
+    #[must_use]
+    pub const fn as_byte(&self) -> u8 {
+        match self {
+            Self::SecondaryStructure => Self::SECONDARY_STRUCTURE,
+            Self::Confidence => Self::CONFIDENCE,
+            Self::Unknown(id) => *id,
+        }
+    }
+}
+
+impl From<u8> for ExtensionType {
+    fn from(type_id: u8) -> Self {
+        match type_id {
+            Self::SECONDARY_STRUCTURE => Self::SecondaryStructure,
+            Self::CONFIDENCE => Self::Confidence,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+// --- Annotation ----------------------------------------------------------------------
+
+/// A single typed, length-prefixed per-record annotation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    type_id: u8,
+    payload: Vec<u8>,
+}
+
+impl Annotation {
+    /// Create a new annotation of the given extension type.
+    #[must_use]
+    pub fn new(extension_type: ExtensionType, payload: Vec<u8>) -> Self {
+        Self {
+            type_id: extension_type.as_byte(),
+            payload,
+        }
+    }
+
+    /// Get the extension type of this annotation.
+    #[must_use]
+    pub fn extension_type(&self) -> ExtensionType {
+        ExtensionType::from(self.type_id)
+    }
+
+    /// Get the raw annotation payload.
+    #[must_use]
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+}
+
+// --- encode/decode ---------------------------------------------------------------------
+
+/// Decode `count` annotations from the extension block.
+///
+/// Unrecognized `type_id`s are decoded into [`ExtensionType::Unknown`]
+/// rather than rejected, so archives written by a newer crate version
+/// remain readable: only `payload_len` bytes are ever consumed per entry.
+pub fn decode_annotations<R: Read>(reader: &mut R, count: usize) -> io::Result<Vec<Annotation>> {
+    let mut annotations = Vec::with_capacity(count);
+    for _ in 0..count {
+        let mut type_id = [0u8; 1];
+        reader.read_exact(&mut type_id)?;
+        let mut len_buf = [0u8; 8];
+        reader.read_exact(&mut len_buf)?;
+        let payload_len = u64::from_le_bytes(len_buf) as usize;
+        let mut payload = vec![0u8; payload_len];
+        reader.read_exact(&mut payload)?;
+        annotations.push(Annotation {
+            type_id: type_id[0],
+            payload,
+        });
+    }
+    Ok(annotations)
+}
+
+/// Encode a record's annotations to the extension block.
+pub fn encode_annotations<W: Write>(writer: &mut W, annotations: &[Annotation]) -> io::Result<()> {
+    for annotation in annotations {
+        writer.write_all(&[annotation.type_id])?;
+        writer.write_all(&(annotation.payload.len() as u64).to_le_bytes())?;
+        writer.write_all(&annotation.payload)?;
+    }
+    Ok(())
+}
+
+// --- tests -------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn unknown_type_id_round_trips_instead_of_erroring() {
+        let annotation = Annotation::new(ExtensionType::Unknown(0xAB), vec![1, 2, 3]);
+        assert_eq!(annotation.extension_type(), ExtensionType::Unknown(0xAB));
+
+        let mut buffer = Vec::new();
+        encode_annotations(&mut buffer, &[annotation.clone()]).unwrap();
+
+        let decoded = decode_annotations(&mut Cursor::new(buffer), 1).unwrap();
+        assert_eq!(decoded, vec![annotation]);
+    }
+
+    #[test]
+    fn decoder_skips_exactly_payload_len_bytes() {
+        let annotations = vec![
+            Annotation::new(ExtensionType::SecondaryStructure, b"(((...)))".to_vec()),
+            Annotation::new(ExtensionType::Confidence, vec![9, 9, 9]),
+        ];
+
+        let mut buffer = Vec::new();
+        encode_annotations(&mut buffer, &annotations).unwrap();
+        buffer.extend_from_slice(b"trailing data belonging to the next block");
+
+        let mut cursor = Cursor::new(buffer);
+        let decoded = decode_annotations(&mut cursor, annotations.len()).unwrap();
+        assert_eq!(decoded, annotations);
+        // (1 + 8 + 9) + (1 + 8 + 3) bytes: the trailing data is left unread.
+        assert_eq!(cursor.position(), 30);
+        assert_eq!(decoded[0].extension_type(), ExtensionType::SecondaryStructure);
+        assert_eq!(decoded[1].payload(), &[9, 9, 9]);
+    }
+}
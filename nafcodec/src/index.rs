@@ -0,0 +1,450 @@
+//! Random-access indexing over the sequence and quality streams.
+//!
+//! A NAF archive stores its sequence (and, optionally, quality) data as a
+//! single zstd stream per block. To support random access without
+//! decompressing the whole archive, the stream can instead be written as a
+//! series of independent zstd frames, each one decompressible on its own.
+//! An [`Index`] pairs the per-record lengths decoded from the `Length`
+//! block with a [`FrameTable`] mapping decompressed offsets to the
+//! compressed offset of the frame that contains them, so a single record
+//! can be recovered by seeking the underlying reader straight to the
+//! frame(s) that cover it.
+
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::{self};
+
+use crate::data::Header;
+
+// --- ByteRange -----------------------------------------------------------------
+
+/// A half-open byte range `[start, end)` within a decompressed stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    /// The number of bytes covered by this range.
+    #[must_use]
+    pub const fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    /// Check whether the range is empty.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+// --- FrameTable ------------------------------------------------------------------
+
+/// A single independent zstd frame boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameEntry {
+    /// Offset of the first decompressed byte of the frame.
+    pub decompressed_offset: u64,
+    /// Offset of the first byte of the frame in the compressed stream.
+    pub compressed_offset: u64,
+}
+
+/// A table of frame boundaries for a seekable zstd stream.
+///
+/// Entries are kept sorted by `decompressed_offset` so the frame covering
+/// a given offset can be found with a binary search.
+#[derive(Debug, Clone, Default)]
+pub struct FrameTable {
+    entries: Vec<FrameEntry>,
+}
+
+impl FrameTable {
+    /// Create an empty frame table.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+
+    /// Record a new frame boundary.
+    ///
+    /// Frames must be pushed in increasing order of `decompressed_offset`.
+    pub fn push(&mut self, decompressed_offset: u64, compressed_offset: u64) {
+        debug_assert!(self
+            .entries
+            .last()
+            .is_none_or(|e| e.decompressed_offset < decompressed_offset));
+        self.entries.push(FrameEntry {
+            decompressed_offset,
+            compressed_offset,
+        });
+    }
+
+    /// Check whether the table has no recorded frames.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Find the frames whose decompressed content covers `range`.
+    ///
+    /// Returns the slice of [`FrameEntry`] that must be decompressed to
+    /// recover all bytes in `range`, since a record may straddle a frame
+    /// boundary.
+    #[must_use]
+    pub fn frames_for(&self, range: ByteRange) -> &[FrameEntry] {
+        if self.entries.is_empty() {
+            return &[];
+        }
+        let start = match self
+            .entries
+            .binary_search_by(|e| e.decompressed_offset.cmp(&range.start))
+        {
+            Ok(i) => i,
+            Err(0) => 0,
+            Err(i) => i - 1,
+        };
+        // `range` is half-open, so a frame starting exactly at `range.end`
+        // holds none of its bytes and must be excluded either way.
+        let end = self
+            .entries
+            .binary_search_by(|e| e.decompressed_offset.cmp(&range.end))
+            .unwrap_or_else(|i| i);
+        &self.entries[start..end.max(start + 1).min(self.entries.len())]
+    }
+
+    /// Rebuild a frame table by walking the independent zstd frames of an
+    /// already-written block.
+    ///
+    /// Nothing in the archive records where a block's frame boundaries
+    /// fall, so when opening an existing archive the table has to be
+    /// rediscovered by decoding each frame just far enough to find where
+    /// the next one starts. `reader` must be positioned at the start of
+    /// the block, and `compressed_len` is the number of compressed bytes
+    /// the block occupies (the counterpart to what
+    /// [`EncoderOptions::write_framed_block`](crate::encoder::EncoderOptions::write_framed_block)
+    /// returns when it wrote the block). `reader` ends up positioned
+    /// right after the block, ready to read whatever follows it.
+    pub fn scan<R: Read + Seek>(reader: &mut R, compressed_len: u64) -> io::Result<Self> {
+        use zstd::stream::raw::Operation;
+
+        let start = reader.stream_position()?;
+        let end = start + compressed_len;
+        let mut table = Self::new();
+        let mut decompressed_offset = 0u64;
+        let mut out_buf = [0u8; 4096];
+        let mut chunk = [0u8; 4096];
+
+        while reader.stream_position()? < end {
+            table.push(decompressed_offset, reader.stream_position()?);
+
+            let mut decoder = zstd::stream::raw::Decoder::new()?;
+            loop {
+                let remaining = end - reader.stream_position()?;
+                if remaining == 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "truncated zstd frame",
+                    ));
+                }
+                let want = remaining.min(chunk.len() as u64) as usize;
+                reader.read_exact(&mut chunk[..want])?;
+
+                let mut input = &chunk[..want];
+                let mut done = false;
+                loop {
+                    let status = decoder.run_on_buffers(input, &mut out_buf)?;
+                    decompressed_offset += status.bytes_written as u64;
+                    input = &input[status.bytes_read..];
+                    if status.remaining == 0 {
+                        // Put back whatever this read consumed past the
+                        // frame's end; it belongs to the next frame.
+                        if !input.is_empty() {
+                            reader.seek(SeekFrom::Current(-(input.len() as i64)))?;
+                        }
+                        done = true;
+                        break;
+                    }
+                    if input.is_empty() {
+                        break;
+                    }
+                }
+                if done {
+                    break;
+                }
+            }
+        }
+
+        Ok(table)
+    }
+}
+
+// --- Index -----------------------------------------------------------------------
+
+/// A random-access index over the records of a NAF archive.
+///
+/// The index is built from the decoded `Length` block: each record length
+/// is turned into a byte range within the concatenated sequence (and,
+/// where applicable, quality) stream via a prefix sum, so `range(i)` is a
+/// constant-time lookup. When the source archive does not carry the
+/// `Length` flag, [`Index::Linear`] should be used instead, and callers
+/// must fall back to a full linear scan to reach a given record.
+#[derive(Debug, Clone)]
+pub enum Index {
+    /// A proper index built from decoded record lengths.
+    Seekable {
+        /// Prefix sums of the record lengths, `offsets[i]..offsets[i + 1]`
+        /// being the byte range of record `i`.
+        offsets: Vec<u64>,
+        /// Frame table for the sequence stream.
+        sequence_frames: FrameTable,
+        /// Frame table for the quality stream, if qualities are stored.
+        quality_frames: Option<FrameTable>,
+    },
+    /// No index is available; records must be reached by linear scan.
+    Linear,
+}
+
+impl Index {
+    /// Build an index from the lengths decoded from the `Length` block.
+    #[must_use]
+    pub fn from_lengths(lengths: &[u64]) -> Self {
+        let mut offsets = Vec::with_capacity(lengths.len() + 1);
+        let mut offset = 0u64;
+        offsets.push(0);
+        for &length in lengths {
+            offset += length;
+            offsets.push(offset);
+        }
+        Self::Seekable {
+            offsets,
+            sequence_frames: FrameTable::new(),
+            quality_frames: None,
+        }
+    }
+
+    /// Build the index appropriate for the given header and decoded lengths.
+    ///
+    /// Returns [`Index::Linear`] when the archive does not carry the
+    /// `Length` flag, since there is then no way to locate a record
+    /// without decoding the records that precede it.
+    #[must_use]
+    pub fn new(header: &Header, lengths: Option<&[u64]>) -> Self {
+        match lengths {
+            Some(lengths) if header.flags().test(crate::data::Flag::Length) => {
+                Self::from_lengths(lengths)
+            }
+            _ => Self::Linear,
+        }
+    }
+
+    /// Attach the sequence stream frame table, if random access is possible.
+    pub fn set_sequence_frames(&mut self, frames: FrameTable) {
+        if let Self::Seekable { sequence_frames, .. } = self {
+            *sequence_frames = frames;
+        }
+    }
+
+    /// Attach the quality stream frame table, if random access is possible.
+    pub fn set_quality_frames(&mut self, frames: FrameTable) {
+        if let Self::Seekable { quality_frames, .. } = self {
+            *quality_frames = Some(frames);
+        }
+    }
+
+    /// The number of records covered by this index, if known.
+    #[must_use]
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            Self::Seekable { offsets, .. } => Some(offsets.len() - 1),
+            Self::Linear => None,
+        }
+    }
+
+    /// Check whether this index covers zero records.
+    ///
+    /// Returns `false` for [`Index::Linear`], since the absence of a
+    /// `Length` block says nothing about whether the archive is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        matches!(self.len(), Some(0))
+    }
+
+    /// Get the byte range of record `i` within the sequence stream.
+    #[must_use]
+    pub fn range(&self, i: usize) -> Option<ByteRange> {
+        match self {
+            Self::Seekable { offsets, .. } => {
+                let start = *offsets.get(i)?;
+                let end = *offsets.get(i + 1)?;
+                Some(ByteRange { start, end })
+            }
+            Self::Linear => None,
+        }
+    }
+
+    /// Check whether [`get_record_bytes`] can be served directly, without a scan.
+    #[must_use]
+    pub const fn is_seekable(&self) -> bool {
+        matches!(self, Self::Seekable { .. })
+    }
+}
+
+// --- random access decoding --------------------------------------------------------
+
+/// Decompress the bytes of record `i` from a seekable sequence stream.
+///
+/// `reader` is seeked to the compressed offset of the frame (or frames, if
+/// the record straddles a boundary) covering the record, decompresses just
+/// those frames, and slices out the requested byte range. Mask units are
+/// not applied: the returned bytes are the raw bytes of `range` in the
+/// sequence stream.
+///
+/// Each frame is decoded with [`Decoder::single_frame`](zstd::stream::read::Decoder::single_frame)
+/// so decompression stops at the frame boundary instead of running to the
+/// end of `reader` — without it, `zstd`'s reader keeps concatenating
+/// frames until EOF, which would decode the rest of the stream (including
+/// unrelated blocks that may follow it) just to recover one record.
+pub fn get_record_bytes<R: Read + Seek>(
+    reader: &mut R,
+    frames: &FrameTable,
+    range: ByteRange,
+) -> io::Result<Vec<u8>> {
+    let covering = frames.frames_for(range);
+    let Some(first) = covering.first() else {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "no frame covers the requested record",
+        ));
+    };
+
+    let base = first.decompressed_offset;
+    let mut buffer = Vec::new();
+    for frame in covering {
+        reader.seek(SeekFrom::Start(frame.compressed_offset))?;
+        let mut decoder = zstd::stream::read::Decoder::new(&mut *reader)?.single_frame();
+        decoder.read_to_end(&mut buffer)?;
+    }
+
+    let local_start = (range.start - base) as usize;
+    let local_end = (range.end - base) as usize;
+    Ok(buffer[local_start..local_end.min(buffer.len())].to_vec())
+}
+
+// --- tests -------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::io::Write;
+
+    #[test]
+    fn index_from_lengths_computes_prefix_sums() {
+        let index = Index::from_lengths(&[10, 20, 30]);
+        assert_eq!(index.len(), Some(3));
+        assert_eq!(index.range(0), Some(ByteRange { start: 0, end: 10 }));
+        assert_eq!(index.range(1), Some(ByteRange { start: 10, end: 30 }));
+        assert_eq!(index.range(2), Some(ByteRange { start: 30, end: 60 }));
+        assert_eq!(index.range(3), None);
+    }
+
+    #[test]
+    fn index_linear_has_no_ranges() {
+        let index = Index::Linear;
+        assert_eq!(index.len(), None);
+        assert!(!index.is_empty());
+        assert_eq!(index.range(0), None);
+        assert!(!index.is_seekable());
+    }
+
+    #[test]
+    fn frame_table_finds_the_covering_frame() {
+        let mut frames = FrameTable::new();
+        frames.push(0, 0);
+        frames.push(100, 42);
+        frames.push(250, 91);
+
+        let covering = frames.frames_for(ByteRange { start: 10, end: 20 });
+        assert_eq!(covering, &[FrameEntry { decompressed_offset: 0, compressed_offset: 0 }]);
+
+        let covering = frames.frames_for(ByteRange { start: 90, end: 110 });
+        assert_eq!(
+            covering,
+            &[
+                FrameEntry { decompressed_offset: 0, compressed_offset: 0 },
+                FrameEntry { decompressed_offset: 100, compressed_offset: 42 },
+            ]
+        );
+    }
+
+    #[test]
+    fn get_record_bytes_only_decodes_the_covering_frame() {
+        let first_frame = zstd::stream::encode_all(&b"hello "[..], 0).unwrap();
+        let second_frame = zstd::stream::encode_all(&b"world"[..], 0).unwrap();
+
+        let mut frames = FrameTable::new();
+        frames.push(0, 0);
+        frames.push(6, first_frame.len() as u64);
+
+        let mut compressed = Cursor::new(Vec::new());
+        compressed.write_all(&first_frame).unwrap();
+        compressed.write_all(&second_frame).unwrap();
+        // Garbage trailing the stream: if `get_record_bytes` ever reads past
+        // its frame it will choke on this instead of silently succeeding.
+        compressed.write_all(b"not zstd data").unwrap();
+
+        let bytes =
+            get_record_bytes(&mut compressed, &frames, ByteRange { start: 6, end: 11 }).unwrap();
+        assert_eq!(bytes, b"world");
+    }
+
+    #[test]
+    fn frames_for_excludes_a_frame_starting_exactly_at_range_end() {
+        let mut frames = FrameTable::new();
+        frames.push(0, 0);
+        frames.push(100, 42);
+
+        // The range ends exactly where the second frame starts, so only
+        // the first frame holds bytes the range actually needs.
+        let covering = frames.frames_for(ByteRange { start: 0, end: 100 });
+        assert_eq!(
+            covering,
+            &[FrameEntry { decompressed_offset: 0, compressed_offset: 0 }]
+        );
+    }
+
+    #[test]
+    fn scan_rebuilds_the_frame_table_written_by_write_framed_block() {
+        use crate::encoder::EncoderOptions;
+
+        let options = EncoderOptions::new();
+        let data = "ACGTACGTAA CCCCGGGGTT AAAACCCCGG".replace(' ', "");
+        let mut compressed = Cursor::new(Vec::new());
+        let written_table = options
+            .write_framed_block(&mut compressed, data.as_bytes(), 10)
+            .unwrap();
+
+        compressed.set_position(0);
+        let compressed_len = compressed.get_ref().len() as u64;
+        let scanned_table = FrameTable::scan(&mut compressed, compressed_len).unwrap();
+
+        // The reader-side scan rediscovers exactly the frame boundaries
+        // the encoder recorded while writing, with no access to them
+        // beyond the compressed bytes themselves.
+        assert_eq!(scanned_table.frames_for(ByteRange { start: 0, end: data.len() as u64 }).len(), 3);
+        for offset in [0, 10, 20] {
+            let range = ByteRange { start: offset, end: offset + 1 };
+            assert_eq!(
+                written_table.frames_for(range),
+                scanned_table.frames_for(range)
+            );
+        }
+
+        compressed.set_position(0);
+        let bytes = get_record_bytes(&mut compressed, &scanned_table, ByteRange { start: 12, end: 22 })
+            .unwrap();
+        assert_eq!(bytes, &data.as_bytes()[12..22]);
+    }
+}
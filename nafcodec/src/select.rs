@@ -0,0 +1,385 @@
+//! Selective extraction of records by accession or comment pattern.
+//!
+//! The `Id` and `Comment` blocks are small relative to the sequence data,
+//! so they can be fully decoded up front to decide which records match a
+//! set of user-supplied patterns. Combined with the [`Index`](crate::index::Index)
+//! built from the `Length` block, sequence (and quality) frames that hold
+//! no matching record never need to be decompressed at all. When no index
+//! is available (the archive does not carry the `Length` flag), selection
+//! instead falls back to a linear scan: the sequence stream is decoded
+//! sequentially from the start, and only the bytes of matching records are
+//! kept.
+
+use std::borrow::Cow;
+use std::io::Read;
+use std::io::Seek;
+use std::io::{self};
+
+use regex::Regex;
+
+use crate::data::Record;
+use crate::index::get_record_bytes;
+use crate::index::Index;
+use crate::source::Source as ByteSource;
+
+// --- Pattern -----------------------------------------------------------------------
+
+/// A single match pattern used to select records, matched against a
+/// record's `id` or `comment` by [`PatternSet::is_match`].
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// A shell-style glob pattern, e.g. `"NC_*"`.
+    Glob(globset::GlobMatcher),
+    /// A regular expression.
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// Compile a glob pattern.
+    pub fn glob(pattern: &str) -> Result<Self, globset::Error> {
+        Ok(Self::Glob(globset::Glob::new(pattern)?.compile_matcher()))
+    }
+
+    /// Compile a regular expression pattern.
+    pub fn regex(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self::Regex(Regex::new(pattern)?))
+    }
+
+    /// Check whether `text` matches this pattern.
+    #[must_use]
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            Self::Glob(matcher) => matcher.is_match(text),
+            Self::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+// --- PatternSet --------------------------------------------------------------------
+
+/// A set of patterns used to select records by `id` or `comment`.
+///
+/// A record is selected if *any* pattern matches its `id`, or its
+/// `comment` when present.
+#[derive(Debug, Clone, Default)]
+pub struct PatternSet {
+    patterns: Vec<Pattern>,
+}
+
+impl PatternSet {
+    /// Create an empty pattern set (matches nothing).
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { patterns: Vec::new() }
+    }
+
+    /// Add a pattern to the set.
+    pub fn push(&mut self, pattern: Pattern) {
+        self.patterns.push(pattern);
+    }
+
+    /// Check whether the given `id`/`comment` pair is selected.
+    #[must_use]
+    pub fn is_match(&self, id: &str, comment: Option<&str>) -> bool {
+        self.patterns
+            .iter()
+            .any(|p| p.is_match(id) || comment.is_some_and(|c| p.is_match(c)))
+    }
+}
+
+impl FromIterator<Pattern> for PatternSet {
+    fn from_iter<I: IntoIterator<Item = Pattern>>(iter: I) -> Self {
+        Self {
+            patterns: iter.into_iter().collect(),
+        }
+    }
+}
+
+// --- Selector ------------------------------------------------------------------------
+
+/// The decoding strategy backing a [`Selector`] when no frame table is
+/// available and the sequence stream must be scanned from the start.
+enum LinearSource<'r, R> {
+    /// `reader` is fully memory-resident: the sequence stream was already
+    /// decoded once, up front, straight from the borrowed slice, so each
+    /// record only needs to copy its share of `decoded` rather than
+    /// re-running the decompressor.
+    Resident { decoded: Vec<u8>, position: usize },
+    /// `reader` is not memory-resident: records are decoded one at a time
+    /// as the underlying decoder is read through.
+    Streaming {
+        decoder: zstd::stream::read::Decoder<'static, io::BufReader<&'r mut R>>,
+    },
+}
+
+/// The decoding strategy backing a [`Selector`].
+enum Source<'r, R> {
+    /// A seekable index is available: only matching records are decoded,
+    /// by seeking straight to the frame(s) that cover them.
+    Seekable(&'r mut R),
+    /// No index is available: the sequence stream is decoded sequentially
+    /// from the start, and every record (matching or not) must be read
+    /// through in order, since there is no frame table to skip ahead with.
+    Linear(LinearSource<'r, R>),
+}
+
+/// Decode a single zstd frame held entirely in `data`, ignoring any bytes
+/// past the end of the frame (which belong to the next block).
+fn decode_resident_sequence(data: &[u8]) -> io::Result<Vec<u8>> {
+    use zstd::stream::raw::Operation;
+
+    let mut decoder = zstd::stream::raw::Decoder::new()?;
+    let mut decompressed = Vec::new();
+    let mut out_buf = [0u8; 4096];
+    let mut input = data;
+
+    loop {
+        let status = decoder.run_on_buffers(input, &mut out_buf)?;
+        decompressed.extend_from_slice(&out_buf[..status.bytes_written]);
+        input = &input[status.bytes_read..];
+        if status.remaining == 0 {
+            return Ok(decompressed);
+        }
+        if input.is_empty() && status.bytes_written == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated zstd frame"));
+        }
+    }
+}
+
+/// An iterator over the records of an archive that match a [`PatternSet`].
+///
+/// Built from the fully-decoded `id`/`comment` pairs of the archive: only
+/// the records that match are recovered from the sequence stream, using
+/// `index` to skip the frames that hold none of them when possible, or
+/// performing a linear scan over the whole sequence stream otherwise.
+/// The quality stream is not read: yielded records have `quality` set to
+/// `None`.
+pub struct Selector<'r, R> {
+    index: &'r Index,
+    ids: Vec<String>,
+    comments: Vec<Option<String>>,
+    lengths: Vec<u64>,
+    matches: Vec<bool>,
+    position: usize,
+    source: Source<'r, R>,
+}
+
+impl<'r, R: ByteSource> Selector<'r, R> {
+    /// Build a selector from the fully-decoded `id`/`comment` blocks.
+    ///
+    /// `ids`, `comments`, and `lengths` must all have the same length, one
+    /// entry per record in the archive, in record order. `reader` must be
+    /// positioned at the start of the sequence stream.
+    pub fn new(
+        reader: &'r mut R,
+        index: &'r Index,
+        ids: Vec<String>,
+        comments: Vec<Option<String>>,
+        lengths: Vec<u64>,
+        patterns: &PatternSet,
+    ) -> io::Result<Self> {
+        let matches = ids
+            .iter()
+            .zip(comments.iter())
+            .map(|(id, comment)| patterns.is_match(id, comment.as_deref()))
+            .collect();
+        let source = match index {
+            Index::Seekable { .. } => Source::Seekable(reader),
+            Index::Linear => {
+                let position = reader.stream_position()? as usize;
+                Source::Linear(match reader.as_slice() {
+                    Some(slice) => {
+                        let decoded = decode_resident_sequence(&slice[position..])?;
+                        LinearSource::Resident { decoded, position: 0 }
+                    }
+                    None => LinearSource::Streaming {
+                        decoder: zstd::stream::read::Decoder::new(reader)?,
+                    },
+                })
+            }
+        };
+        Ok(Self {
+            index,
+            ids,
+            comments,
+            lengths,
+            matches,
+            position: 0,
+            source,
+        })
+    }
+
+    /// The number of records that matched the pattern set.
+    #[must_use]
+    pub fn matched(&self) -> usize {
+        self.matches.iter().filter(|m| **m).count()
+    }
+}
+
+impl<'r, R: ByteSource> Iterator for Selector<'r, R> {
+    type Item = io::Result<Record<'static>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.position < self.matches.len() {
+            let i = self.position;
+            self.position += 1;
+            let is_match = self.matches[i];
+
+            let sequence = match &mut self.source {
+                Source::Seekable(reader) => {
+                    if !is_match {
+                        continue;
+                    }
+                    let Index::Seekable { sequence_frames, .. } = self.index else {
+                        unreachable!("Source::Seekable is only built from Index::Seekable")
+                    };
+                    let Some(range) = self.index.range(i) else {
+                        return Some(Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "index has no byte range for this record",
+                        )));
+                    };
+                    match get_record_bytes(reader, sequence_frames, range) {
+                        Ok(bytes) => bytes,
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+                Source::Linear(LinearSource::Resident { decoded, position }) => {
+                    let len = self.lengths[i] as usize;
+                    if *position + len > decoded.len() {
+                        return Some(Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "sequence block ends before all records are read",
+                        )));
+                    }
+                    let buffer = decoded[*position..*position + len].to_vec();
+                    *position += len;
+                    if !is_match {
+                        continue;
+                    }
+                    buffer
+                }
+                Source::Linear(LinearSource::Streaming { decoder }) => {
+                    // No frame table to skip ahead with: every record's
+                    // bytes must be read through in order, even when it
+                    // does not match, to keep the decoder positioned at
+                    // the start of the next record.
+                    let mut buffer = vec![0u8; self.lengths[i] as usize];
+                    if let Err(e) = decoder.read_exact(&mut buffer) {
+                        return Some(Err(e));
+                    }
+                    if !is_match {
+                        continue;
+                    }
+                    buffer
+                }
+            };
+
+            return Some(Ok(Record {
+                id: Some(Cow::Owned(self.ids[i].clone())),
+                comment: self.comments[i].clone().map(Cow::Owned),
+                sequence: Some(Cow::Owned(sequence)),
+                quality: None,
+                length: Some(self.lengths[i]),
+                annotations: Vec::new(),
+            }));
+        }
+        None
+    }
+}
+
+// --- tests -------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn patterns(globs: &[&str]) -> PatternSet {
+        globs.iter().map(|g| Pattern::glob(g).unwrap()).collect()
+    }
+
+    #[test]
+    fn pattern_set_matches_id_or_comment() {
+        let set = patterns(&["NC_*"]);
+        assert!(set.is_match("NC_001", None));
+        assert!(!set.is_match("XM_001", Some("NC_001 mitochondrion")));
+        assert!(set.is_match("XM_001", Some("NC_001")));
+    }
+
+    #[test]
+    fn selector_falls_back_to_a_linear_scan_without_an_index() {
+        // `Cursor<Vec<u8>>` is memory-resident, so this also exercises the
+        // `LinearSource::Resident` decode-once path.
+        let sequence = zstd::stream::encode_all(&b"AAAACCCCGGGG"[..], 0).unwrap();
+        let mut reader = Cursor::new(sequence);
+
+        let ids = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let comments = vec![None, None, None];
+        let lengths = vec![4, 4, 4];
+        let patterns = patterns(&["b"]);
+
+        let mut selector =
+            Selector::new(&mut reader, &Index::Linear, ids, comments, lengths, &patterns).unwrap();
+        let matched: Vec<_> = selector.by_ref().collect::<io::Result<Vec<_>>>().unwrap();
+
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].id.as_deref(), Some("b"));
+        assert_eq!(matched[0].sequence.as_deref(), Some(&b"CCCC"[..]));
+    }
+
+    /// A reader that forwards `Read`/`Seek` but never exposes a borrowable
+    /// slice, mirroring `source.rs`'s own `NotResident` test type, to
+    /// exercise `LinearSource::Streaming`.
+    struct NotResident<R>(R);
+
+    impl<R: Read> Read for NotResident<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl<R: Seek> Seek for NotResident<R> {
+        fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+            self.0.seek(pos)
+        }
+    }
+
+    impl<R: Read + Seek> ByteSource for NotResident<R> {}
+
+    #[test]
+    fn selector_falls_back_to_streaming_decode_without_a_resident_source() {
+        let sequence = zstd::stream::encode_all(&b"AAAACCCCGGGG"[..], 0).unwrap();
+        let mut reader = NotResident(Cursor::new(sequence));
+
+        let ids = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let comments = vec![None, None, None];
+        let lengths = vec![4, 4, 4];
+        let patterns = patterns(&["b"]);
+
+        let mut selector =
+            Selector::new(&mut reader, &Index::Linear, ids, comments, lengths, &patterns).unwrap();
+        let matched: Vec<_> = selector.by_ref().collect::<io::Result<Vec<_>>>().unwrap();
+
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].id.as_deref(), Some("b"));
+        assert_eq!(matched[0].sequence.as_deref(), Some(&b"CCCC"[..]));
+    }
+
+    #[test]
+    fn selector_surfaces_decode_errors_instead_of_stopping_silently() {
+        // Truncate the compressed stream so decoding the only record fails.
+        let sequence = zstd::stream::encode_all(&b"AAAA"[..], 0).unwrap();
+        let truncated = &sequence[..sequence.len() - 2];
+        let mut reader = NotResident(Cursor::new(truncated.to_vec()));
+
+        let ids = vec!["a".to_string()];
+        let comments = vec![None];
+        let lengths = vec![4];
+        let patterns = patterns(&["a"]);
+
+        let mut selector =
+            Selector::new(&mut reader, &Index::Linear, ids, comments, lengths, &patterns).unwrap();
+        assert!(selector.next().unwrap().is_err());
+    }
+}
@@ -0,0 +1,96 @@
+//! A generalized byte source for block readers.
+//!
+//! Block input used to be hard-coded as a two-variant
+//! `PyFileWrapper` enum (a plain [`File`](std::fs::File) or a Python file
+//! object). [`Source`] replaces that closed enum with a small trait, so
+//! the crate can also accept a memory-mapped file or an in-memory
+//! `&[u8]`.
+//!
+//! Decompressing a zstd block always produces freshly-allocated output,
+//! so no source can hand back a `Cow::Borrowed` sequence or quality
+//! string: the win for sources where [`Source::as_slice`] returns `Some`
+//! is decoding straight from the borrowed slice instead of going through
+//! a `Read`/`BufReader` indirection, and decoding each block once up
+//! front rather than re-decoding it per record (see [`select`](crate::select)'s
+//! resident-decode path). Sources that only support `Read`, such as a
+//! Python file object, fall back to decoding through the reader as they
+//! are consumed.
+
+use std::io::Read;
+use std::io::Seek;
+
+/// A source of bytes a block reader can read from and seek within.
+///
+/// Implement [`Source::as_slice`] whenever the whole input is already
+/// resident in memory (a slice, or a memory map) to let callers borrow
+/// directly from it instead of copying into an owned buffer.
+pub trait Source: Read + Seek {
+    /// Borrow the entire input as a contiguous slice, if possible.
+    ///
+    /// Returns `None` for sources that are not fully memory-resident,
+    /// such as a plain [`File`](std::fs::File) or a Python file object,
+    /// in which case callers must read (and own) the bytes they need.
+    fn as_slice(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+impl Source for std::fs::File {}
+
+impl Source for std::io::Cursor<&[u8]> {
+    fn as_slice(&self) -> Option<&[u8]> {
+        Some(self.get_ref())
+    }
+}
+
+impl Source for std::io::Cursor<Vec<u8>> {
+    fn as_slice(&self) -> Option<&[u8]> {
+        Some(self.get_ref().as_slice())
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Source for std::io::Cursor<memmap2::Mmap> {
+    fn as_slice(&self) -> Option<&[u8]> {
+        Some(self.get_ref())
+    }
+}
+
+// --- tests -------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn slice_and_vec_sources_are_memory_resident() {
+        let data: &[u8] = b"ACGT";
+        let cursor = Cursor::new(data);
+        assert_eq!(Source::as_slice(&cursor), Some(b"ACGT".as_slice()));
+
+        let cursor = Cursor::new(data.to_vec());
+        assert_eq!(Source::as_slice(&cursor), Some(b"ACGT".as_slice()));
+    }
+
+    #[test]
+    fn non_memory_resident_sources_default_to_no_borrowable_slice() {
+        // `File` relies on the trait's default `as_slice` implementation,
+        // same as any other source (e.g. a Python file object) that can
+        // only be read through, not borrowed from directly.
+        struct NotResident;
+        impl Read for NotResident {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Ok(0)
+            }
+        }
+        impl Seek for NotResident {
+            fn seek(&mut self, _pos: std::io::SeekFrom) -> std::io::Result<u64> {
+                Ok(0)
+            }
+        }
+        impl Source for NotResident {}
+
+        assert_eq!(NotResident.as_slice(), None);
+    }
+}
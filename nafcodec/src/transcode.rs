@@ -0,0 +1,343 @@
+//! Streaming conversion between NAF archives.
+//!
+//! [`transcode`] reads one NAF archive and writes another, optionally
+//! changing the [`FormatVersion`], the zstd compression level, or the
+//! `line_length`: each block (ids, lengths, mask, sequence, quality) is
+//! re-framed independently, one block at a time. Each block is decoded
+//! whole into memory before it is re-compressed, so the sequence block,
+//! which holds every sequence of the archive, is held in memory in full.
+
+use std::fmt;
+use std::io::Read;
+use std::io::Write;
+use std::io::{self};
+
+use crate::data::Flag;
+use crate::data::FormatVersion;
+use crate::data::Header;
+
+// --- TranscodeOptions --------------------------------------------------------------
+
+/// Options controlling how an archive is re-encoded by [`transcode`].
+#[derive(Debug, Clone)]
+pub struct TranscodeOptions {
+    format_version: FormatVersion,
+    compression_level: i32,
+    line_length: Option<u64>,
+}
+
+impl TranscodeOptions {
+    /// Create new options targeting `format_version` at the default
+    /// zstd compression level, keeping the source `line_length`.
+    #[must_use]
+    pub const fn new(format_version: FormatVersion) -> Self {
+        Self {
+            format_version,
+            compression_level: 19,
+            line_length: None,
+        }
+    }
+
+    /// Set the zstd compression level to use when re-framing blocks.
+    #[must_use]
+    pub const fn compression_level(mut self, level: i32) -> Self {
+        self.compression_level = level;
+        self
+    }
+
+    /// Override the `line_length` stored in the rewritten header.
+    #[must_use]
+    pub const fn line_length(mut self, line_length: u64) -> Self {
+        self.line_length = Some(line_length);
+        self
+    }
+
+    /// The target format version.
+    #[must_use]
+    pub const fn format_version(&self) -> FormatVersion {
+        self.format_version
+    }
+}
+
+// --- TranscodeError ------------------------------------------------------------------
+
+/// An error occurring while transcoding a NAF archive.
+#[derive(Debug)]
+pub enum TranscodeError {
+    /// The source header sets a flag that has no representation in the
+    /// target format version (for instance, downgrading an archive that
+    /// uses the reserved `Extended` flag to `V1`).
+    UnsupportedDowngrade(Flag),
+    /// An I/O error occurred while reading or writing a block.
+    Io(io::Error),
+}
+
+impl fmt::Display for TranscodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnsupportedDowngrade(flag) => write!(
+                f,
+                "cannot downgrade to FormatVersion::V1: archive uses {:?}, which V1 cannot represent",
+                flag,
+            ),
+            Self::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for TranscodeError {}
+
+impl From<io::Error> for TranscodeError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+// --- transcode -----------------------------------------------------------------------
+
+/// Check that `header` can be losslessly represented in `target`.
+///
+/// Downgrading from `V2` to `V1` fails when the source uses a flag that
+/// `V1` has no way to encode, namely the reserved `Extended` flag.
+fn check_downgrade(header: &Header, target: FormatVersion) -> Result<(), TranscodeError> {
+    if header.format_version() == FormatVersion::V2 && target == FormatVersion::V1 {
+        for &flag in &[Flag::Extended] {
+            if header.flags().test(flag) {
+                return Err(TranscodeError::UnsupportedDowngrade(flag));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Re-encode a NAF archive read from `reader` into `writer`.
+///
+/// `header` describes the blocks present in `reader`, which must be
+/// positioned right after the source header (`reader` carries only the
+/// blocks, not the header bytes themselves). A header reflecting
+/// `options` is written to `writer` first, then each block is decoded
+/// and immediately re-compressed and written in turn, so memory use
+/// stays proportional to a single block rather than the whole archive.
+/// Blocks absent from the header (for instance, a missing `Quality`
+/// block) are skipped on both sides.
+///
+/// # Errors
+///
+/// Returns [`TranscodeError::UnsupportedDowngrade`] if `options` targets
+/// [`FormatVersion::V1`] but the source archive relies on a `V2`-only
+/// header feature, and [`TranscodeError::Io`] for any underlying read or
+/// write failure.
+pub fn transcode<R: Read, W: Write>(
+    reader: R,
+    mut writer: W,
+    header: &Header,
+    options: &TranscodeOptions,
+) -> Result<(), TranscodeError> {
+    check_downgrade(header, options.format_version())?;
+
+    let target_header = Header {
+        format_version: options.format_version(),
+        line_length: options.line_length.unwrap_or_else(|| header.line_length()),
+        ..header.clone()
+    };
+    target_header.write(&mut writer)?;
+
+    let mut reader = BlockReader::new(reader);
+    for &flag in Flag::values() {
+        if !header.flags().test(flag) {
+            continue;
+        }
+        transcode_block(&mut reader, &mut writer, flag, options)?;
+    }
+
+    Ok(())
+}
+
+/// A reader that carries unconsumed read-ahead bytes forward between
+/// sequential single-frame decodes.
+///
+/// `reader` has no way to seek back, so [`decode_one_frame`] cannot afford
+/// to read past the end of the frame it's decoding: the convenience
+/// [`zstd::stream::read::Decoder`] wraps its reader in its own ~128KB
+/// `BufReader`, and any bytes that `BufReader` physically reads past the
+/// frame boundary (which belong to the *next* block) are lost once that
+/// decoder is dropped at the end of each call. `BlockReader` reads in
+/// ordinary-sized chunks for throughput, but whenever a chunk reads past
+/// the frame being decoded, the unconsumed tail is kept in `pending`
+/// instead of being discarded, so the next call picks up exactly where
+/// the previous one left off.
+struct BlockReader<R> {
+    inner: R,
+    pending: Vec<u8>,
+}
+
+impl<R: Read> BlockReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            pending: Vec::new(),
+        }
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.pending.is_empty() {
+            let n = usize::min(buf.len(), self.pending.len());
+            buf[..n].copy_from_slice(&self.pending[..n]);
+            self.pending.drain(..n);
+            return Ok(n);
+        }
+        self.inner.read(buf)
+    }
+
+    fn push_back(&mut self, bytes: &[u8]) {
+        debug_assert!(self.pending.is_empty());
+        self.pending = bytes.to_vec();
+    }
+}
+
+/// Decode the next zstd frame from `reader`, one block's worth of bytes.
+fn decode_one_frame<R: Read>(reader: &mut BlockReader<R>) -> io::Result<Vec<u8>> {
+    use zstd::stream::raw::Operation;
+
+    let mut decoder = zstd::stream::raw::Decoder::new()?;
+    let mut decompressed = Vec::new();
+    let mut out_buf = [0u8; 4096];
+    let mut chunk = [0u8; 4096];
+
+    loop {
+        let read = reader.fill(&mut chunk)?;
+        if read == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated zstd frame"));
+        }
+        let mut input = &chunk[..read];
+        loop {
+            let status = decoder.run_on_buffers(input, &mut out_buf)?;
+            decompressed.extend_from_slice(&out_buf[..status.bytes_written]);
+            input = &input[status.bytes_read..];
+            if status.remaining == 0 {
+                if !input.is_empty() {
+                    reader.push_back(input);
+                }
+                return Ok(decompressed);
+            }
+            if input.is_empty() {
+                break;
+            }
+        }
+    }
+}
+
+fn transcode_block<R: Read, W: Write>(
+    reader: &mut BlockReader<R>,
+    writer: &mut W,
+    _block: Flag,
+    options: &TranscodeOptions,
+) -> Result<(), TranscodeError> {
+    let block = decode_one_frame(reader)?;
+
+    let mut encoder = zstd::stream::write::Encoder::new(writer, options.compression_level)?;
+    encoder.write_all(&block)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+// --- tests -------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Flags;
+    use std::io::Cursor;
+
+    #[test]
+    fn transcode_preserves_every_block_when_several_flags_are_set() {
+        let mut flags = Flags::new();
+        flags.set(Flag::Id);
+        flags.set(Flag::Sequence);
+        let header = Header {
+            flags,
+            ..Header::default()
+        };
+
+        // `Flag::values()` visits `Sequence` before `Id`, so the blocks
+        // must appear in that order in the source stream.
+        let mut source = Vec::new();
+        source.extend(zstd::stream::encode_all(&b"ACGTACGT"[..], 0).unwrap());
+        source.extend(zstd::stream::encode_all(&b"seq1\nseq2"[..], 0).unwrap());
+
+        let options = TranscodeOptions::new(FormatVersion::V1);
+        let mut output = Cursor::new(Vec::new());
+        transcode(Cursor::new(source), &mut output, &header, &options).unwrap();
+
+        let mut reader = &output.get_ref()[..];
+        let written_header = Header::read(&mut reader).unwrap();
+        assert!(written_header.flags().test(Flag::Sequence));
+        assert!(written_header.flags().test(Flag::Id));
+
+        let mut sequence_decoder = zstd::stream::read::Decoder::with_buffer(&mut reader)
+            .unwrap()
+            .single_frame();
+        let mut sequence = Vec::new();
+        sequence_decoder.read_to_end(&mut sequence).unwrap();
+        assert_eq!(sequence, b"ACGTACGT");
+        drop(sequence_decoder);
+
+        let mut ids_decoder = zstd::stream::read::Decoder::new(&mut reader)
+            .unwrap()
+            .single_frame();
+        let mut ids = Vec::new();
+        ids_decoder.read_to_end(&mut ids).unwrap();
+        assert_eq!(ids, b"seq1\nseq2");
+    }
+
+    #[test]
+    fn transcode_overrides_line_length_in_the_written_header() {
+        let header = Header {
+            line_length: 60,
+            ..Header::default()
+        };
+
+        let options = TranscodeOptions::new(FormatVersion::V1).line_length(80);
+        let mut output = Cursor::new(Vec::new());
+        transcode(Cursor::new(Vec::new()), &mut output, &header, &options).unwrap();
+
+        let mut reader = &output.get_ref()[..];
+        let written_header = Header::read(&mut reader).unwrap();
+        assert_eq!(written_header.line_length(), 80);
+    }
+
+    #[test]
+    fn decode_one_frame_does_not_lose_bytes_past_the_frame() {
+        let mut source = Vec::new();
+        source.extend(zstd::stream::encode_all(&b"ACGT"[..], 0).unwrap());
+        source.extend(zstd::stream::encode_all(&b"seq1\nseq2"[..], 0).unwrap());
+        source.extend_from_slice(b"trailing data belonging to a later, unrelated block");
+
+        // The frames are shorter than `BlockReader`'s read-ahead chunk, so
+        // the first `fill` call reads well past the first frame's end;
+        // those extra bytes must still be available to the second call.
+        let mut reader = BlockReader::new(&source[..]);
+        let block = decode_one_frame(&mut reader).unwrap();
+        assert_eq!(block, b"ACGT");
+
+        let next = decode_one_frame(&mut reader).unwrap();
+        assert_eq!(next, b"seq1\nseq2");
+    }
+
+    #[test]
+    fn check_downgrade_rejects_extended_flag() {
+        let mut flags = Flags::new();
+        flags.set(Flag::Extended);
+        let header = Header {
+            format_version: FormatVersion::V2,
+            flags,
+            ..Header::default()
+        };
+
+        let err = check_downgrade(&header, FormatVersion::V1).unwrap_err();
+        assert!(matches!(
+            err,
+            TranscodeError::UnsupportedDowngrade(Flag::Extended)
+        ));
+    }
+}
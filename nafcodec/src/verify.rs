@@ -0,0 +1,279 @@
+//! Integrity verification for decoded NAF archives.
+//!
+//! [`verify`] cross-checks the structural invariants a well-formed
+//! archive must satisfy after decoding: the record count against the
+//! header, each sequence length against the `Length` block, each quality
+//! length against its sequence length, and,
+//! given the raw compressed bytes of a block, its zstd frame content
+//! checksum, so truncated or corrupted archives can be caught before
+//! downstream use.
+
+use std::fmt;
+
+use crate::data::Flag;
+use crate::data::Header;
+use crate::data::Record;
+use crate::data::Size;
+
+// --- Mismatch ------------------------------------------------------------------------
+
+/// A single integrity invariant that failed to hold.
+#[derive(Debug, Clone)]
+pub enum Mismatch {
+    /// The number of decoded records does not match
+    /// [`Header::number_of_sequences`].
+    RecordCount { expected: u64, actual: u64 },
+    /// A decoded sequence length does not match its `Length` block entry.
+    SequenceLength {
+        record: usize,
+        expected: u64,
+        actual: u64,
+    },
+    /// A decoded quality string length does not match its sequence length.
+    QualityLength {
+        record: usize,
+        expected: u64,
+        actual: u64,
+    },
+    /// A zstd frame content checksum did not validate.
+    ChecksumFailed { block: String },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::RecordCount { expected, actual } => write!(
+                f,
+                "expected {} records per the header, decoded {}",
+                expected, actual
+            ),
+            Self::SequenceLength {
+                record,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "record {}: sequence length {} does not match Length block entry {}",
+                record, actual, expected
+            ),
+            Self::QualityLength {
+                record,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "record {}: quality length {} does not match sequence length {}",
+                record, actual, expected
+            ),
+            Self::ChecksumFailed { block } => {
+                write!(f, "{}: zstd frame checksum did not validate", block)
+            }
+        }
+    }
+}
+
+// --- VerifyReport ----------------------------------------------------------------------
+
+/// The result of running [`verify`] on a decoded archive.
+///
+/// Reuses [`Size`] to report the original (expected) and recovered
+/// (actual) counts, alongside any mismatches found: the number of
+/// records, then the sequence and quality byte totals.
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    sizes: Vec<Size>,
+    mismatches: Vec<Mismatch>,
+}
+
+impl VerifyReport {
+    /// The per-block size report, original vs. recovered byte counts.
+    #[must_use]
+    pub fn sizes(&self) -> &[Size] {
+        &self.sizes
+    }
+
+    /// The invariant violations found, if any.
+    #[must_use]
+    pub fn mismatches(&self) -> &[Mismatch] {
+        &self.mismatches
+    }
+
+    /// Check whether every invariant held.
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+// --- verify --------------------------------------------------------------------------
+
+/// Cross-check the structural invariants of a fully-decoded archive.
+///
+/// Checks, in order: for each `(name, compressed_bytes)` pair in
+/// `raw_blocks`, that the block's zstd frame content checksum validates,
+/// pushing a [`Mismatch::ChecksumFailed`] named after it otherwise; that
+/// `records.len()` matches `header.number_of_sequences()`; that each
+/// record's decoded sequence length matches its entry from the `Length`
+/// block, when present; and that each quality string's length matches
+/// its sequence length, whenever the `Quality` flag is set. `raw_blocks`
+/// is typically empty unless the caller kept the compressed bytes around
+/// specifically to check this.
+#[must_use]
+pub fn verify(
+    header: &Header,
+    records: &[Record],
+    lengths: Option<&[u64]>,
+    raw_blocks: &[(&str, &[u8])],
+) -> VerifyReport {
+    let mut report = VerifyReport::default();
+
+    for &(block, bytes) in raw_blocks {
+        if zstd::stream::decode_all(bytes).is_err() {
+            report.mismatches.push(Mismatch::ChecksumFailed {
+                block: block.to_string(),
+            });
+        }
+    }
+
+    let expected_count = header.number_of_sequences();
+    let actual_count = records.len() as u64;
+    report.sizes.push(Size::new(
+        "records".to_string(),
+        expected_count,
+        Some(actual_count),
+    ));
+    if expected_count != actual_count {
+        report.mismatches.push(Mismatch::RecordCount {
+            expected: expected_count,
+            actual: actual_count,
+        });
+    }
+
+    let mut sequence_total = 0u64;
+    let mut quality_total = 0u64;
+    for (i, record) in records.iter().enumerate() {
+        let actual_length = record.sequence.as_ref().map_or(0, |s| s.len() as u64);
+        sequence_total += actual_length;
+
+        if let Some(lengths) = lengths {
+            if let Some(&expected_length) = lengths.get(i) {
+                if expected_length != actual_length {
+                    report.mismatches.push(Mismatch::SequenceLength {
+                        record: i,
+                        expected: expected_length,
+                        actual: actual_length,
+                    });
+                }
+            }
+        }
+
+        if header.flags().test(Flag::Quality) {
+            let quality_length = record.quality.as_ref().map_or(0, |q| q.len() as u64);
+            quality_total += quality_length;
+            if quality_length != actual_length {
+                report.mismatches.push(Mismatch::QualityLength {
+                    record: i,
+                    expected: actual_length,
+                    actual: quality_length,
+                });
+            }
+        }
+    }
+
+    if let Some(lengths) = lengths {
+        let expected_total: u64 = lengths.iter().sum();
+        report.sizes.push(Size::new(
+            "sequence".to_string(),
+            expected_total,
+            Some(sequence_total),
+        ));
+    }
+    if header.flags().test(Flag::Quality) {
+        report
+            .sizes
+            .push(Size::new("quality".to_string(), sequence_total, Some(quality_total)));
+    }
+
+    report
+}
+
+// --- tests -------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Flags;
+    use std::borrow::Cow;
+    use std::io::Write;
+
+    fn record(sequence: &'static [u8]) -> Record<'static> {
+        Record {
+            sequence: Some(Cow::Borrowed(sequence)),
+            ..Record::default()
+        }
+    }
+
+    #[test]
+    fn verify_passes_on_consistent_archive() {
+        let mut flags = Flags::new();
+        flags.set(Flag::Length);
+        let header = Header {
+            flags,
+            number_of_sequences: 2,
+            ..Header::default()
+        };
+        let records = vec![record(b"ACGT"), record(b"AC")];
+        let report = verify(&header, &records, Some(&[4, 2]), &[]);
+        assert!(report.is_ok());
+        assert!(report.mismatches().is_empty());
+    }
+
+    #[test]
+    fn verify_flags_record_count_and_length_mismatches() {
+        let header = Header {
+            number_of_sequences: 3,
+            ..Header::default()
+        };
+        let records = vec![record(b"ACGT"), record(b"AC")];
+        let report = verify(&header, &records, Some(&[4, 3]), &[]);
+
+        assert!(!report.is_ok());
+        assert!(matches!(
+            report.mismatches()[0],
+            Mismatch::RecordCount {
+                expected: 3,
+                actual: 2
+            }
+        ));
+        assert!(matches!(
+            report.mismatches()[1],
+            Mismatch::SequenceLength {
+                record: 1,
+                expected: 3,
+                actual: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn verify_flags_a_corrupted_frame_checksum() {
+        let mut encoder = zstd::stream::write::Encoder::new(Vec::new(), 0).unwrap();
+        encoder.include_checksum(true).unwrap();
+        encoder.write_all(b"ACGT").unwrap();
+        let mut good = encoder.finish().unwrap();
+
+        // Flip a bit in the trailing checksum so the block decodes fine
+        // (the corruption is past the content) but fails the checksum.
+        let last = good.len() - 1;
+        good[last] ^= 0xFF;
+
+        let header = Header::default();
+        let report = verify(&header, &[], None, &[("sequence", &good)]);
+
+        assert!(!report.is_ok());
+        assert!(matches!(
+            &report.mismatches()[0],
+            Mismatch::ChecksumFailed { block } if block == "sequence"
+        ));
+    }
+}